@@ -1,23 +1,182 @@
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_TABLE, edwards::CompressedEdwardsY, scalar::Scalar,
+};
 use failure::Fail;
+use hmac::{Hmac, Mac, NewMac};
 use rand::{rngs::OsRng, Rng};
 use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha512, Sha512Trunc256};
+use std::convert::TryFrom;
 use x25519_dalek;
 
 use ekiden_runtime::{
-    common::{crypto::signature::Signature, runtime::RuntimeId},
+    common::{
+        crypto::{
+            mrae::deoxysii::{DeoxysII, NONCE_SIZE},
+            signature::{PublicKey as SignerPublicKey, Signature},
+        },
+        runtime::RuntimeId,
+    },
     impl_bytes, runtime_api,
 };
 
+/// HMAC-SHA-512/256, used as the PRF for the key manager's HKDF.
+type Kdf = Hmac<Sha512Trunc256>;
+
+/// Fixed salt used when extracting the master secret into a pseudorandom key.
+///
+/// Using a fixed, publicly known salt is standard HKDF practice when the
+/// input keying material (the `MasterSecret`) is already high entropy; it
+/// just domain-separates this KDF from any other use of HMAC-SHA-512/256.
+const KDF_EXTRACT_SALT: &[u8] = b"ekiden-keymanager-master-secret";
+
+/// Domain separation tag for deriving a contract's state encryption key.
+const KDF_INFO_STATE: &[u8] = b"state";
+/// Domain separation tag for deriving a contract's x25519 input secret key.
+const KDF_INFO_X25519_SK: &[u8] = b"x25519-sk";
+/// Reserved domain separation tag for future derived key material.
+#[allow(unused)]
+const KDF_INFO_RESERVED: &[u8] = b"reserved";
+
+/// Domain separation tag for deriving the AEAD key used to seal a
+/// `MasterSecret` for replication to a peer.
+const REPLICATION_AEAD_CONTEXT: &[u8] = b"ekiden-keymanager-replication-aead-key";
+
+/// Domain separation tag for deriving a contract's base Ed25519 blinding
+/// scalar, the un-blinded identity that `get_blinded_public_key` presents
+/// distinct, mutually-unlinkable faces of.
+const KDF_INFO_ED25519_BLIND_BASE: &[u8] = b"ed25519-blind-base";
+/// Domain separation tag for deriving a contract's blinding seed, which is
+/// mixed with each caller-supplied `blinding_context` to derive that
+/// context's blinding factor.
+const KDF_INFO_BLIND_SEED: &[u8] = b"blind-seed";
+
+/// HKDF-Extract: condense `ikm` into a pseudorandom key under `salt`.
+fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    let mut mac = Kdf::new_varkey(salt).expect("hmac can take a key of any size");
+    mac.update(ikm);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// HKDF-Expand-ish: derive a single 32-byte output block from `prk` and `info`.
+///
+/// Each call site uses a distinct `info`, so a single one-block expansion
+/// (rather than the general multi-block HKDF-Expand) is sufficient here.
+fn hkdf_expand(prk: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut mac = Kdf::new_varkey(prk).expect("hmac can take a key of any size");
+    mac.update(info);
+    mac.update(&[0x01]);
+    let mut output = [0u8; 32];
+    output.copy_from_slice(&mac.finalize().into_bytes()[..32]);
+    output
+}
+
 impl_bytes!(ContractId, 32, "A 256-bit contract identifier.");
 impl_bytes!(PrivateKey, 32, "A private key.");
 impl_bytes!(PublicKey, 32, "A public key.");
 impl_bytes!(StateKey, 32, "A state key.");
 impl_bytes!(MasterSecret, 32, "A 256 bit master secret.");
 
+/// Context used for signing a key manager policy document.
+pub const POLICY_SIGN_CONTEXT: [u8; 8] = *b"EkKmPlcy";
+
+/// A peer key manager authorized by policy to replicate the `MasterSecret`.
+///
+/// Binds the peer's long-term signing identity to the x25519 key it will
+/// present as `replicate_master_secret`'s `peer_pk` argument, so an
+/// implementer can check "is this `peer_pk` one of the policy-approved
+/// peers" instead of sealing to whatever x25519 key a caller happens to
+/// present.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AllowedPeer {
+    /// The peer's signature public key (its long-term identity).
+    pub signer: SignerPublicKey,
+    /// The x25519 public key this peer must present to
+    /// `replicate_master_secret` to receive the sealed `MasterSecret`.
+    pub x25519_pk: PublicKey,
+}
+
+/// A policy gating which enclaves may request long-term keys from this key
+/// manager, and which peer key managers may replicate its `MasterSecret`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PolicySGX {
+    /// Monotonically increasing version; a higher serial always supersedes
+    /// a lower one, so a stale policy can never be replayed forward.
+    pub serial: u32,
+    /// `RuntimeId`s of the enclaves allowed to call `get_or_create_keys` and
+    /// friends on this key manager.
+    pub enclaves_allowed: Vec<RuntimeId>,
+    /// Peer key managers allowed to call `replicate_master_secret` against
+    /// this key manager.
+    pub peers_allowed: Vec<AllowedPeer>,
+}
+
+impl PolicySGX {
+    /// Check whether `peer_pk` is the x25519 key bound to one of this
+    /// policy's allowed peers.
+    ///
+    /// `replicate_master_secret` must check this before calling
+    /// `SealedSecret::seal` for `peer_pk`; otherwise any caller could mint
+    /// its own x25519 keypair, present the public half as `peer_pk`, and
+    /// receive the `MasterSecret` sealed to a key it already controls.
+    pub fn is_peer_allowed(&self, peer_pk: &PublicKey) -> bool {
+        self.peers_allowed
+            .iter()
+            .any(|peer| &peer.x25519_pk == peer_pk)
+    }
+}
+
+/// A `PolicySGX` together with the signatures authorizing it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedPolicySGX {
+    /// The policy document.
+    pub policy: PolicySGX,
+    /// Signatures over `policy`, one per authorizing signer.
+    pub signatures: Vec<Signature>,
+}
+
+impl SignedPolicySGX {
+    /// Verify that `policy` is authentically signed by at least one of
+    /// `trusted_signers` and that its `serial` is strictly greater than
+    /// `last_accepted_serial`, returning the policy on success.
+    ///
+    /// The serial check is what actually makes "a stale policy can never
+    /// be replayed forward" true: callers must track the serial of the
+    /// last policy they accepted (0 before any policy has been accepted)
+    /// and pass it back in on every subsequent `init`/update.
+    pub fn verify(
+        &self,
+        trusted_signers: &[SignerPublicKey],
+        last_accepted_serial: u32,
+    ) -> Result<&PolicySGX, KeyManagerError> {
+        if self.policy.serial <= last_accepted_serial {
+            return Err(KeyManagerError::PolicyRollback);
+        }
+
+        let message = serde_cbor::to_vec(&self.policy).map_err(|_| KeyManagerError::PolicyInvalid)?;
+        let authorized = self.signatures.iter().any(|signature| {
+            trusted_signers
+                .iter()
+                .any(|signer| signer.verify(&POLICY_SIGN_CONTEXT, &message, signature).is_ok())
+        });
+        if !authorized {
+            return Err(KeyManagerError::PolicyInvalid);
+        }
+        Ok(&self.policy)
+    }
+}
+
 /// Key manager initialization request.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct InitRequest {
-    // TODO: Policy, peers, checksum, etc.
+    /// Signed policy gating who may request keys and who may replicate the
+    /// `MasterSecret`.
+    pub policy: SignedPolicySGX,
+    /// Checksum the caller expects the key manager's derived-key namespace
+    /// to match once initialized; compared against the checksum computed
+    /// from the (possibly freshly generated) `MasterSecret`.
+    #[serde(with = "serde_bytes")]
+    pub checksum: Vec<u8>,
 }
 
 /// Key manager initialization response.
@@ -30,6 +189,103 @@ pub struct InitResponse {
     pub checksum: Vec<u8>,
 }
 
+impl InitResponse {
+    /// Compute the checksum binding `master` to the `policy` it was (or
+    /// will be) initialized under.
+    ///
+    /// `init` computes this over the namespace being initialized and
+    /// compares it against the caller-supplied `InitRequest::checksum`,
+    /// refusing to proceed on mismatch; peers compare it again after
+    /// replicating the `MasterSecret` to confirm they landed on the same
+    /// state.
+    pub fn checksum_for(master: &MasterSecret, policy: &PolicySGX) -> Vec<u8> {
+        let policy_bytes = serde_cbor::to_vec(policy).expect("PolicySGX always serializes");
+        let mut mac = Kdf::new_varkey(master.as_ref()).expect("hmac can take a key of any size");
+        mac.update(b"checksum");
+        mac.update(&policy_bytes);
+        mac.finalize().into_bytes().to_vec()
+    }
+}
+
+/// A `MasterSecret` sealed for a single peer, produced by
+/// `replicate_master_secret` and consumed by `load_replicated_secret`.
+///
+/// Sealing is HPKE over x25519: the source generates an ephemeral x25519
+/// keypair, does Diffie-Hellman against the peer's static `PublicKey`, and
+/// runs the shared secret through HKDF to derive an AEAD key that seals the
+/// `MasterSecret`. Only `ephemeral_pk`, `nonce` and `ciphertext` ever leave
+/// the enclave; the `MasterSecret` itself never appears in plaintext
+/// outside of it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SealedSecret {
+    /// Ephemeral x25519 public key generated for this seal.
+    pub ephemeral_pk: PublicKey,
+    /// AEAD nonce.
+    #[serde(with = "serde_bytes")]
+    pub nonce: Vec<u8>,
+    /// AEAD-sealed `MasterSecret`.
+    #[serde(with = "serde_bytes")]
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedSecret {
+    /// Seal `master` so that only the holder of `peer_sk` (matching
+    /// `peer_pk`) can recover it. Backs `replicate_master_secret`.
+    pub fn seal(peer_pk: &PublicKey, master: &MasterSecret) -> Self {
+        let mut rng = OsRng::new().unwrap();
+        let ephemeral_sk = x25519_dalek::StaticSecret::new(&mut rng);
+        let ephemeral_pk = x25519_dalek::PublicKey::from(&ephemeral_sk);
+
+        let peer_pk = x25519_dalek::PublicKey::from(peer_pk.0);
+        let shared_secret = ephemeral_sk.diffie_hellman(&peer_pk);
+
+        let prk = hkdf_extract(REPLICATION_AEAD_CONTEXT, shared_secret.as_bytes());
+        let aead_key = hkdf_expand(&prk, b"aead-key");
+        let aead = DeoxysII::new(&aead_key);
+
+        let mut nonce = [0u8; NONCE_SIZE];
+        rng.fill(&mut nonce);
+        let ciphertext = aead.seal(&nonce, master.as_ref().to_vec(), vec![]);
+
+        SealedSecret {
+            ephemeral_pk: PublicKey(*ephemeral_pk.as_bytes()),
+            nonce: nonce.to_vec(),
+            ciphertext,
+        }
+    }
+
+    /// Reverse `seal` using the receiver's static x25519 secret key.
+    /// Backs `load_replicated_secret`, which then re-derives the checksum
+    /// from the recovered `MasterSecret` and compares it against the one in
+    /// its own `InitRequest` before trusting it.
+    pub fn open(&self, receiver_sk: &PrivateKey) -> Result<MasterSecret, KeyManagerError> {
+        let receiver_sk = x25519_dalek::StaticSecret::from(receiver_sk.0);
+        let ephemeral_pk = x25519_dalek::PublicKey::from(self.ephemeral_pk.0);
+        let shared_secret = receiver_sk.diffie_hellman(&ephemeral_pk);
+
+        let prk = hkdf_extract(REPLICATION_AEAD_CONTEXT, shared_secret.as_bytes());
+        let aead_key = hkdf_expand(&prk, b"aead-key");
+        let aead = DeoxysII::new(&aead_key);
+
+        if self.nonce.len() != NONCE_SIZE {
+            return Err(KeyManagerError::ReplicationFailed);
+        }
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce.copy_from_slice(&self.nonce);
+
+        let plaintext = aead
+            .open(&nonce, self.ciphertext.clone(), vec![])
+            .map_err(|_| KeyManagerError::ReplicationFailed)?;
+        if plaintext.len() != 32 {
+            return Err(KeyManagerError::ReplicationFailed);
+        }
+
+        let mut secret = MasterSecret::default();
+        secret.0.copy_from_slice(&plaintext);
+        Ok(secret)
+    }
+}
+
 /// Request runtime/contract id tuple.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct RequestIds {
@@ -54,6 +310,100 @@ impl RequestIds {
     }
 }
 
+/// Request runtime/contract id tuple, scoped to a specific epoch.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EphemeralRequestIds {
+    /// Runtime ID.
+    pub runtime_id: RuntimeId,
+    /// Contract ID.
+    pub contract_id: ContractId,
+    /// Epoch the ephemeral key is scoped to.
+    pub epoch: u64,
+}
+
+impl EphemeralRequestIds {
+    pub fn new(runtime_id: RuntimeId, contract_id: ContractId, epoch: u64) -> Self {
+        Self {
+            runtime_id,
+            contract_id,
+            epoch,
+        }
+    }
+
+    pub fn to_cache_key(&self) -> Vec<u8> {
+        let mut k = self.runtime_id.as_ref().to_vec();
+        k.extend_from_slice(self.contract_id.as_ref());
+        k.extend_from_slice(&self.epoch.to_le_bytes());
+        k
+    }
+}
+
+/// Request runtime/contract id tuple, scoped to a blinding context.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BlindedRequestIds {
+    /// Runtime ID.
+    pub runtime_id: RuntimeId,
+    /// Contract ID.
+    pub contract_id: ContractId,
+    /// Context the blinded key is scoped to; distinct contexts yield
+    /// mutually-unlinkable public keys for the same contract.
+    #[serde(with = "serde_bytes")]
+    pub blinding_context: Vec<u8>,
+}
+
+impl BlindedRequestIds {
+    pub fn new(runtime_id: RuntimeId, contract_id: ContractId, blinding_context: Vec<u8>) -> Self {
+        Self {
+            runtime_id,
+            contract_id,
+            blinding_context,
+        }
+    }
+
+    pub fn to_cache_key(&self) -> Vec<u8> {
+        let mut k = self.runtime_id.as_ref().to_vec();
+        k.extend_from_slice(self.contract_id.as_ref());
+        k.extend_from_slice(&self.blinding_context);
+        k
+    }
+}
+
+/// Number of epochs past expiry that `get_public_key` still serves an
+/// ephemeral `SignedPublicKey` for, so ciphertexts encrypted right before a
+/// rotation boundary can still be decrypted. Configurable per deployment;
+/// this is just the default, for callers to pass as
+/// `is_epoch_key_available`'s `grace_epochs` when they have no
+/// deployment-specific override.
+pub const EPHEMERAL_KEY_GRACE_EPOCHS: u64 = 1;
+
+/// The `SignedPublicKey::timestamp` for an ephemeral key scoped to `epoch`:
+/// the Unix timestamp at which `epoch` ends, i.e. `epoch_interval_secs`
+/// seconds after the start of `epoch` (which is the start of `epoch + 1`),
+/// counted from `genesis_timestamp` (the Unix timestamp at which epoch 0
+/// began). `get_ephemeral_keys` and
+/// `get_ephemeral_public_key` must set the `SignedPublicKey::timestamp` they
+/// return to this value, so a verifier can tell when the key stops being
+/// current for `epoch` without needing to track the epoch schedule itself.
+pub fn epoch_end_timestamp(epoch: u64, genesis_timestamp: u64, epoch_interval_secs: u64) -> u64 {
+    genesis_timestamp + (epoch + 1) * epoch_interval_secs
+}
+
+/// Whether `get_public_key`/`get_ephemeral_public_key` should still serve a
+/// `SignedPublicKey` whose `timestamp` is `key_epoch_end` (as computed by
+/// `epoch_end_timestamp`), given the current time `now` and a grace window
+/// of `grace_epochs` epochs past expiry (pass `EPHEMERAL_KEY_GRACE_EPOCHS`
+/// for the default). Lets ciphertexts encrypted right before a rotation
+/// boundary still be decrypted for a little while after the epoch turns
+/// over, instead of the key becoming unavailable the instant it expires.
+pub fn is_epoch_key_available(
+    key_epoch_end: u64,
+    now: u64,
+    epoch_interval_secs: u64,
+    grace_epochs: u64,
+) -> bool {
+    now <= key_epoch_end + grace_epochs * epoch_interval_secs
+}
+
 /// Keys for a contract.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ContractKey {
@@ -80,6 +430,120 @@ impl ContractKey {
         )
     }
 
+    /// Deterministically derive the `ContractKey` for `ids` from `master`.
+    ///
+    /// Every replica of the key manager that holds the same `MasterSecret`
+    /// derives the same `input_keypair` and `state_key` for a given
+    /// `RequestIds`, via HKDF (HMAC-SHA-512/256 extract-then-expand) with
+    /// `info = runtime_id || contract_id || domain_tag`. This is what backs
+    /// `get_or_create_keys`.
+    pub fn derive(master: &MasterSecret, ids: &RequestIds) -> Self {
+        let prk = hkdf_extract(KDF_EXTRACT_SALT, master.as_ref());
+
+        let mut info = ids.runtime_id.as_ref().to_vec();
+        info.extend_from_slice(ids.contract_id.as_ref());
+
+        Self::derive_from_prk(&prk, info)
+    }
+
+    /// Derive the ephemeral `ContractKey` for `ids`'s epoch, folding the
+    /// epoch number into the KDF `info` alongside `runtime_id ||
+    /// contract_id`. Backs `get_ephemeral_keys`; rotating the epoch rotates
+    /// this key without touching the long-term key from `derive`.
+    pub fn derive_for_epoch(master: &MasterSecret, ids: &EphemeralRequestIds) -> Self {
+        let prk = hkdf_extract(KDF_EXTRACT_SALT, master.as_ref());
+
+        let mut info = ids.runtime_id.as_ref().to_vec();
+        info.extend_from_slice(ids.contract_id.as_ref());
+        info.extend_from_slice(&ids.epoch.to_le_bytes());
+
+        Self::derive_from_prk(&prk, info)
+    }
+
+    /// Shared tail of `derive`/`derive_for_epoch`: expand the extracted
+    /// `prk` into an x25519 keypair and a state key under `info`.
+    fn derive_from_prk(prk: &[u8], info: Vec<u8>) -> Self {
+        let mut sk_info = info.clone();
+        sk_info.extend_from_slice(KDF_INFO_X25519_SK);
+        let mut sk_bytes = hkdf_expand(prk, &sk_info);
+        // Clamp, per the x25519 "clamp" convention for static secrets.
+        sk_bytes[0] &= 248;
+        sk_bytes[31] &= 127;
+        sk_bytes[31] |= 64;
+        let sk = x25519_dalek::StaticSecret::from(sk_bytes);
+        let pk = x25519_dalek::PublicKey::from(&sk);
+
+        let mut state_info = info;
+        state_info.extend_from_slice(KDF_INFO_STATE);
+        let state_key = StateKey(hkdf_expand(prk, &state_info));
+
+        ContractKey::new(PublicKey(*pk.as_bytes()), PrivateKey(sk.to_bytes()), state_key)
+    }
+
+    /// Derive the blinded, mutually-unlinkable Ed25519 signing key
+    /// presented for `ids.blinding_context`: a scalar blinding factor
+    /// `b = H(blind_seed || blinding_context) mod L` is folded into the
+    /// contract's base Ed25519 scalar, yielding a blinded secret scalar
+    /// `b * base_scalar` and blinded public point `b * A`. The key manager
+    /// only ever stores `base_scalar` and `blind_seed`; each
+    /// `blinding_context` yields a stable but unlinkable identity. Backs
+    /// `get_blinded_public_key`.
+    pub fn derive_blinded_keypair(
+        master: &MasterSecret,
+        ids: &BlindedRequestIds,
+    ) -> (Scalar, curve25519_dalek::edwards::EdwardsPoint) {
+        let base_scalar = Self::derive_ed25519_blind_base(master, ids);
+        let b = Self::derive_blinding_factor(master, ids);
+
+        let blinded_secret = b * base_scalar;
+        let blinded_public = &blinded_secret * &ED25519_BASEPOINT_TABLE;
+        (blinded_secret, blinded_public)
+    }
+
+    /// Recover the contract's base Ed25519 public point from a public point
+    /// previously blinded for `ids` by `derive_blinded_keypair`, by
+    /// multiplying by the modular inverse of the same blinding factor `b`.
+    /// This is the "unblind" half of the blind-key scheme, but it is
+    /// KM-internal/audit-only: it takes `&MasterSecret`, which only the key
+    /// manager itself ever holds, so no relying party can call it directly.
+    /// If a relying party needs to confirm a blinded key traces back to a
+    /// contract's long-term identity, the key manager must do the
+    /// unblinding on its behalf and tell it only the resulting boolean.
+    pub fn unblind_public_key(
+        master: &MasterSecret,
+        ids: &BlindedRequestIds,
+        blinded_public: &curve25519_dalek::edwards::EdwardsPoint,
+    ) -> curve25519_dalek::edwards::EdwardsPoint {
+        let b = Self::derive_blinding_factor(master, ids);
+        b.invert() * blinded_public
+    }
+
+    /// Derive the contract's base (un-blinded) Ed25519 signing scalar.
+    fn derive_ed25519_blind_base(master: &MasterSecret, ids: &BlindedRequestIds) -> Scalar {
+        let prk = hkdf_extract(KDF_EXTRACT_SALT, master.as_ref());
+
+        let mut base_info = ids.runtime_id.as_ref().to_vec();
+        base_info.extend_from_slice(ids.contract_id.as_ref());
+        base_info.extend_from_slice(KDF_INFO_ED25519_BLIND_BASE);
+        Scalar::from_bytes_mod_order(hkdf_expand(&prk, &base_info))
+    }
+
+    /// Derive the per-`blinding_context` scalar blinding factor `b`.
+    fn derive_blinding_factor(master: &MasterSecret, ids: &BlindedRequestIds) -> Scalar {
+        let prk = hkdf_extract(KDF_EXTRACT_SALT, master.as_ref());
+
+        let mut seed_info = ids.runtime_id.as_ref().to_vec();
+        seed_info.extend_from_slice(ids.contract_id.as_ref());
+        seed_info.extend_from_slice(KDF_INFO_BLIND_SEED);
+        let blind_seed = hkdf_expand(&prk, &seed_info);
+
+        let mut mac = Kdf::new_varkey(&blind_seed).expect("hmac can take a key of any size");
+        mac.update(&ids.blinding_context);
+        let mut factor_bytes = [0u8; 32];
+        factor_bytes.copy_from_slice(&mac.finalize().into_bytes()[..32]);
+        Scalar::from_bytes_mod_order(factor_bytes)
+    }
+
     /// Create a set of `ContractKey`.
     pub fn new(pk: PublicKey, sk: PrivateKey, k: StateKey) -> Self {
         Self {
@@ -100,6 +564,74 @@ impl ContractKey {
     }
 }
 
+/// Sign `message` with `blinded_secret` (as derived by
+/// `ContractKey::derive_blinded_keypair`), producing a Schnorr-style
+/// signature that verifies against `blinded_secret * B` via
+/// `blind_schnorr_verify`.
+///
+/// This is the matching "sign" half of the blind-key scheme: not RFC 8032
+/// EdDSA, since EdDSA's nonce is derived by hashing the *raw* secret key
+/// bytes, which doesn't commute with scalar blinding (multiplying the base
+/// scalar by `b`). The nonce here is instead derived from the blinded
+/// scalar and the message, which does.
+pub fn blind_schnorr_sign(blinded_secret: &Scalar, message: &[u8]) -> Vec<u8> {
+    let mut nonce_hash = Sha512::new();
+    nonce_hash.update(blinded_secret.as_bytes());
+    nonce_hash.update(message);
+    let nonce = Scalar::from_hash(nonce_hash);
+
+    let r_point = &nonce * &ED25519_BASEPOINT_TABLE;
+    let r_bytes = r_point.compress();
+
+    let mut challenge_hash = Sha512::new();
+    challenge_hash.update(r_bytes.as_bytes());
+    challenge_hash.update(message);
+    let challenge = Scalar::from_hash(challenge_hash);
+
+    let s = nonce + challenge * blinded_secret;
+
+    let mut signature = Vec::with_capacity(64);
+    signature.extend_from_slice(r_bytes.as_bytes());
+    signature.extend_from_slice(s.as_bytes());
+    signature
+}
+
+/// Verify a `blind_schnorr_sign` signature over `message` against the
+/// blinded public point `blinded_public` (as derived by
+/// `ContractKey::derive_blinded_keypair`).
+pub fn blind_schnorr_verify(
+    blinded_public: &curve25519_dalek::edwards::EdwardsPoint,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    if signature.len() != 64 {
+        return false;
+    }
+
+    let mut r_bytes = [0u8; 32];
+    r_bytes.copy_from_slice(&signature[..32]);
+    let mut s_bytes = [0u8; 32];
+    s_bytes.copy_from_slice(&signature[32..]);
+
+    let r_point = match CompressedEdwardsY(r_bytes).decompress() {
+        Some(point) => point,
+        None => return false,
+    };
+    let s = match Scalar::from_canonical_bytes(s_bytes) {
+        Some(s) => s,
+        None => return false,
+    };
+
+    let mut challenge_hash = Sha512::new();
+    challenge_hash.update(&r_bytes);
+    challenge_hash.update(message);
+    let challenge = Scalar::from_hash(challenge_hash);
+
+    let lhs = &s * &ED25519_BASEPOINT_TABLE;
+    let rhs = r_point + challenge * blinded_public;
+    lhs == rhs
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct InputKeyPair {
     /// Pk
@@ -125,6 +657,63 @@ impl InputKeyPair {
 /// Context used for the public key signature.
 pub const PUBLIC_KEY_CONTEXT: [u8; 8] = *b"EkKmPubK";
 
+/// A signature scheme the key manager's signing key may use to attest
+/// `SignedPublicKey`s.
+///
+/// Runtimes whose on-chain consumers only verify a particular algorithm
+/// (e.g. an EVM runtime whose contracts check secp256k1 signatures) can
+/// require that scheme instead of translating a fixed Ed25519 attestation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum SignatureScheme {
+    /// Ed25519, the key manager's historical default.
+    Ed25519,
+    /// Plain Ed25519 over a SHA-512 prehash of `(key || timestamp)` rather
+    /// than the message itself, for callers that need to sign a digest of
+    /// something larger than the attestation message.
+    ///
+    /// This is NOT RFC 8032 Ed25519ph: real Ed25519ph replaces Ed25519's
+    /// internal `SHA-512(dom2(...) || message)` hash-to-scalar step with one
+    /// over `dom2(...) || SHA-512(message)`, which needs access to Ed25519's
+    /// internals to implement and isn't what an opaque `sign`/`verify` API
+    /// gives us. An external verifier expecting RFC 8032 Ed25519ph
+    /// interop cannot verify signatures under this scheme.
+    Ed25519Prehashed,
+    /// secp256k1 (k256), for EVM-runtime compatibility.
+    Secp256k1,
+    /// NIST P-256.
+    P256,
+}
+
+impl SignatureScheme {
+    /// Preference order, most to least preferred, used by a verifier that
+    /// accepts more than one scheme and wants a canonical first choice.
+    ///
+    /// There is deliberately no scheme here for the blind-Schnorr keys
+    /// `get_blinded_public_key` mints (see `blind_schnorr_sign`): a
+    /// `blind_schnorr_sign` signature only proves the caller holds the
+    /// blinded secret, not that the key manager ever attested the blinded
+    /// key, so accepting it through `SignedPublicKey::verify` would let
+    /// anyone self-sign an arbitrary point and have it accepted as a KM
+    /// credential. A `SignedPublicKey` for a blinded key must still carry
+    /// one of the schemes below, signed by the key manager's own identity,
+    /// the same way `get_public_key` attests an unblinded one.
+    pub const PREFERENCE_ORDER: &'static [SignatureScheme] = &[
+        SignatureScheme::Ed25519,
+        SignatureScheme::Ed25519Prehashed,
+        SignatureScheme::P256,
+        SignatureScheme::Secp256k1,
+    ];
+
+    /// Pick the most preferred scheme that appears in `allowed`, or `None`
+    /// if `allowed` is empty.
+    pub fn preferred(allowed: &[SignatureScheme]) -> Option<SignatureScheme> {
+        Self::PREFERENCE_ORDER
+            .iter()
+            .find(|scheme| allowed.contains(scheme))
+            .copied()
+    }
+}
+
 /// Signed public key.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SignedPublicKey {
@@ -132,8 +721,81 @@ pub struct SignedPublicKey {
     pub key: PublicKey,
     /// Timestamp representing the expiry of the returned key.
     pub timestamp: Option<u64>,
-    /// Sign(sk, (key || timestamp)) from the key manager.
-    pub signature: Signature,
+    /// Scheme `signature` was produced with.
+    pub scheme: SignatureScheme,
+    /// Sign(sk, (key || timestamp)) from the key manager, under `scheme`.
+    ///
+    /// Variable-length: a raw 64-byte Ed25519 signature for
+    /// `Ed25519`/`Ed25519Prehashed`, but a DER-encoded ECDSA signature
+    /// (typically 70-72 bytes) for `Secp256k1`/`P256`, which a fixed-size
+    /// `Signature` can't represent.
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
+}
+
+impl SignedPublicKey {
+    /// Verify `self.signature` over `(key || timestamp)` against `signer`,
+    /// dispatching on `self.scheme` rather than assuming Ed25519.
+    ///
+    /// Rejects schemes not present in `allowed_schemes`, so a runtime can
+    /// pin down exactly which signature algorithm(s) it trusts from the key
+    /// manager.
+    pub fn verify(
+        &self,
+        signer: &[u8],
+        allowed_schemes: &[SignatureScheme],
+    ) -> Result<(), KeyManagerError> {
+        if !allowed_schemes.contains(&self.scheme) {
+            return Err(KeyManagerError::SignatureSchemeNotAllowed);
+        }
+
+        let mut message = self.key.as_ref().to_vec();
+        if let Some(timestamp) = self.timestamp {
+            message.extend_from_slice(&timestamp.to_le_bytes());
+        }
+
+        match self.scheme {
+            SignatureScheme::Ed25519 | SignatureScheme::Ed25519Prehashed => {
+                // Ed25519Prehashed verifies over the SHA-512 prehash of the
+                // message rather than the message itself; Ed25519 verifies
+                // the message directly. Both use the same underlying
+                // context-based Ed25519 verify, so this is not RFC 8032
+                // Ed25519ph (see the `Ed25519Prehashed` doc comment).
+                let to_verify = match self.scheme {
+                    SignatureScheme::Ed25519Prehashed => Sha512::digest(&message).to_vec(),
+                    _ => message,
+                };
+
+                let signer = SignerPublicKey::from_bytes(signer)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                let signature = Signature::try_from(self.signature.as_slice())
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                signer
+                    .verify(&PUBLIC_KEY_CONTEXT, &to_verify, &signature)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)
+            }
+            SignatureScheme::Secp256k1 => {
+                use k256::ecdsa::signature::Verifier;
+                let verify_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(signer)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                let sig = k256::ecdsa::Signature::from_der(&self.signature)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                verify_key
+                    .verify(&message, &sig)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)
+            }
+            SignatureScheme::P256 => {
+                use p256::ecdsa::signature::Verifier;
+                let verify_key = p256::ecdsa::VerifyingKey::from_sec1_bytes(signer)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                let sig = p256::ecdsa::Signature::from_der(&self.signature)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)?;
+                verify_key
+                    .verify(&message, &sig)
+                    .map_err(|_| KeyManagerError::SignatureInvalid)
+            }
+        }
+    }
 }
 
 /// Key manager error.
@@ -145,12 +807,340 @@ pub enum KeyManagerError {
     NotInitialized,
     #[fail(display = "key manager is already initialized")]
     AlreadyInitialized,
+    #[fail(display = "key manager policy is invalid or not signed by a trusted authority")]
+    PolicyInvalid,
+    #[fail(display = "key manager policy serial would roll back the last accepted policy")]
+    PolicyRollback,
+    #[fail(display = "key manager replication checksum mismatch")]
+    ChecksumMismatch,
+    #[fail(display = "key manager failed to replicate master secret from peer")]
+    ReplicationFailed,
+    #[fail(display = "signature scheme not in the verifier's allowed set")]
+    SignatureSchemeNotAllowed,
+    #[fail(display = "signature is invalid")]
+    SignatureInvalid,
 }
 
 runtime_api! {
+    pub fn init(InitRequest) -> InitResponse;
+
+    pub fn replicate_master_secret(PublicKey) -> SealedSecret;
+
+    pub fn load_replicated_secret(SealedSecret) -> InitResponse;
+
     pub fn get_or_create_keys(RequestIds) -> ContractKey;
 
     pub fn get_public_key(RequestIds) -> Option<SignedPublicKey>;
 
     pub fn get_long_term_public_key(RequestIds) -> Option<SignedPublicKey>;
+
+    pub fn get_ephemeral_keys(EphemeralRequestIds) -> ContractKey;
+
+    pub fn get_ephemeral_public_key(EphemeralRequestIds) -> Option<SignedPublicKey>;
+
+    pub fn get_blinded_public_key(BlindedRequestIds) -> SignedPublicKey;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_key_derive_is_deterministic() {
+        let master = MasterSecret([3u8; 32]);
+        let ids = RequestIds::new(RuntimeId::default(), ContractId::default());
+
+        let a = ContractKey::derive(&master, &ids);
+        let b = ContractKey::derive(&master, &ids);
+
+        assert_eq!(
+            a.input_keypair.get_pk().as_ref(),
+            b.input_keypair.get_pk().as_ref()
+        );
+        assert_eq!(
+            a.input_keypair.get_sk().as_ref(),
+            b.input_keypair.get_sk().as_ref()
+        );
+        assert_eq!(a.state_key.as_ref(), b.state_key.as_ref());
+    }
+
+    #[test]
+    fn test_contract_key_derive_differs_per_contract() {
+        let master = MasterSecret([3u8; 32]);
+        let ids_a = RequestIds::new(RuntimeId::default(), ContractId([1u8; 32]));
+        let ids_b = RequestIds::new(RuntimeId::default(), ContractId([2u8; 32]));
+
+        let a = ContractKey::derive(&master, &ids_a);
+        let b = ContractKey::derive(&master, &ids_b);
+
+        assert_ne!(a.state_key.as_ref(), b.state_key.as_ref());
+    }
+
+    #[test]
+    fn test_sealed_secret_round_trip() {
+        let mut rng = OsRng::new().unwrap();
+        let peer_sk = x25519_dalek::StaticSecret::new(&mut rng);
+        let peer_pk = PublicKey(*x25519_dalek::PublicKey::from(&peer_sk).as_bytes());
+
+        let master = MasterSecret([5u8; 32]);
+        let sealed = SealedSecret::seal(&peer_pk, &master);
+
+        let recovered = sealed
+            .open(&PrivateKey(peer_sk.to_bytes()))
+            .expect("sealing for peer_pk must be openable with the matching secret key");
+        assert_eq!(recovered.as_ref(), master.as_ref());
+    }
+
+    #[test]
+    fn test_sealed_secret_rejects_wrong_receiver() {
+        let mut rng = OsRng::new().unwrap();
+        let peer_sk = x25519_dalek::StaticSecret::new(&mut rng);
+        let peer_pk = PublicKey(*x25519_dalek::PublicKey::from(&peer_sk).as_bytes());
+        let wrong_sk = x25519_dalek::StaticSecret::new(&mut rng);
+
+        let master = MasterSecret([5u8; 32]);
+        let sealed = SealedSecret::seal(&peer_pk, &master);
+
+        assert!(sealed.open(&PrivateKey(wrong_sk.to_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_verify_ed25519_round_trip() {
+        use ekiden_runtime::common::crypto::signature::PrivateKey;
+
+        let sk = PrivateKey::generate(&mut OsRng::new().unwrap());
+
+        let key = PublicKey::default();
+        let timestamp = Some(12345u64);
+        let mut message = key.as_ref().to_vec();
+        message.extend_from_slice(&timestamp.unwrap().to_le_bytes());
+
+        let signature = sk.sign(&PUBLIC_KEY_CONTEXT, &message);
+        let signed = SignedPublicKey {
+            key,
+            timestamp,
+            scheme: SignatureScheme::Ed25519,
+            signature: signature.as_ref().to_vec(),
+        };
+
+        signed
+            .verify(sk.public_key().as_ref(), &[SignatureScheme::Ed25519])
+            .expect("a genuine Ed25519 signature must verify");
+    }
+
+    #[test]
+    fn test_verify_ed25519_prehashed_round_trip() {
+        use ekiden_runtime::common::crypto::signature::PrivateKey;
+
+        let sk = PrivateKey::generate(&mut OsRng::new().unwrap());
+
+        let key = PublicKey::default();
+        let timestamp = Some(12345u64);
+        let mut message = key.as_ref().to_vec();
+        message.extend_from_slice(&timestamp.unwrap().to_le_bytes());
+        let prehash = Sha512::digest(&message).to_vec();
+
+        let signature = sk.sign(&PUBLIC_KEY_CONTEXT, &prehash);
+        let signed = SignedPublicKey {
+            key,
+            timestamp,
+            scheme: SignatureScheme::Ed25519Prehashed,
+            signature: signature.as_ref().to_vec(),
+        };
+
+        signed
+            .verify(
+                sk.public_key().as_ref(),
+                &[SignatureScheme::Ed25519Prehashed],
+            )
+            .expect("a genuine Ed25519Prehashed signature must verify");
+    }
+
+    #[test]
+    fn test_verify_secp256k1_round_trip() {
+        use k256::ecdsa::{signature::Signer, Signature as K256Signature, SigningKey, VerifyingKey};
+
+        let signing_key = SigningKey::random(&mut OsRng::new().unwrap());
+        let verify_key = VerifyingKey::from(&signing_key);
+
+        let key = PublicKey::default();
+        let timestamp = Some(12345u64);
+        let mut message = key.as_ref().to_vec();
+        message.extend_from_slice(&timestamp.unwrap().to_le_bytes());
+
+        let signature: K256Signature = signing_key.sign(&message);
+        let signed = SignedPublicKey {
+            key,
+            timestamp,
+            scheme: SignatureScheme::Secp256k1,
+            signature: signature.to_der().as_bytes().to_vec(),
+        };
+
+        signed
+            .verify(
+                verify_key.to_encoded_point(true).as_bytes(),
+                &[SignatureScheme::Secp256k1],
+            )
+            .expect("a genuine secp256k1 signature must verify");
+    }
+
+    #[test]
+    fn test_verify_p256_round_trip() {
+        use p256::ecdsa::{signature::Signer, Signature as P256Signature, SigningKey, VerifyingKey};
+
+        let signing_key = SigningKey::random(&mut OsRng::new().unwrap());
+        let verify_key = VerifyingKey::from(&signing_key);
+
+        let key = PublicKey::default();
+        let message = key.as_ref().to_vec();
+
+        let signature: P256Signature = signing_key.sign(&message);
+        let signed = SignedPublicKey {
+            key,
+            timestamp: None,
+            scheme: SignatureScheme::P256,
+            signature: signature.to_der().as_bytes().to_vec(),
+        };
+
+        signed
+            .verify(
+                verify_key.to_encoded_point(true).as_bytes(),
+                &[SignatureScheme::P256],
+            )
+            .expect("a genuine P256 signature must verify");
+    }
+
+    #[test]
+    fn test_blind_schnorr_round_trip() {
+        // `blind_schnorr_sign`/`blind_schnorr_verify` are exercised directly
+        // here, not through `SignedPublicKey::verify`: a blind-Schnorr
+        // signature only proves possession of the blinded secret, not a
+        // key-manager attestation, so it is deliberately not one of the
+        // schemes `SignedPublicKey::verify` accepts.
+        let master = MasterSecret([7u8; 32]);
+        let ids = BlindedRequestIds::new(
+            RuntimeId::default(),
+            ContractId::default(),
+            b"relying-party-a".to_vec(),
+        );
+
+        let (blinded_secret, blinded_public) = ContractKey::derive_blinded_keypair(&master, &ids);
+        let message = b"some message attesting to the blinded key";
+        let signature = blind_schnorr_sign(&blinded_secret, message);
+
+        assert!(blind_schnorr_verify(&blinded_public, message, &signature));
+        assert!(!blind_schnorr_verify(&blinded_public, b"a different message", &signature));
+    }
+
+    #[test]
+    fn test_unblind_recovers_base_public_key() {
+        let master = MasterSecret([9u8; 32]);
+        let base_ids_a = BlindedRequestIds::new(
+            RuntimeId::default(),
+            ContractId::default(),
+            b"relying-party-a".to_vec(),
+        );
+        let base_ids_b = BlindedRequestIds::new(
+            RuntimeId::default(),
+            ContractId::default(),
+            b"relying-party-b".to_vec(),
+        );
+
+        let (_, blinded_a) = ContractKey::derive_blinded_keypair(&master, &base_ids_a);
+        let (_, blinded_b) = ContractKey::derive_blinded_keypair(&master, &base_ids_b);
+
+        // Distinct contexts must yield distinct, unlinkable public keys...
+        assert_ne!(blinded_a.compress(), blinded_b.compress());
+
+        // ...that both unblind back to the same base identity.
+        let unblinded_a = ContractKey::unblind_public_key(&master, &base_ids_a, &blinded_a);
+        let unblinded_b = ContractKey::unblind_public_key(&master, &base_ids_b, &blinded_b);
+        assert_eq!(unblinded_a.compress(), unblinded_b.compress());
+    }
+
+    #[test]
+    fn test_signed_policy_rejects_rollback() {
+        let policy = PolicySGX {
+            serial: 1,
+            enclaves_allowed: vec![],
+            peers_allowed: vec![],
+        };
+        let signed = SignedPolicySGX {
+            policy,
+            signatures: vec![],
+        };
+
+        // Serial 1 does not exceed an already-accepted serial of 1.
+        assert!(match signed.verify(&[], 1) {
+            Err(KeyManagerError::PolicyRollback) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_policy_is_peer_allowed() {
+        let allowed_pk = PublicKey([1u8; 32]);
+        let other_pk = PublicKey([2u8; 32]);
+        let policy = PolicySGX {
+            serial: 1,
+            enclaves_allowed: vec![],
+            peers_allowed: vec![AllowedPeer {
+                signer: SignerPublicKey::default(),
+                x25519_pk: allowed_pk,
+            }],
+        };
+
+        assert!(policy.is_peer_allowed(&allowed_pk));
+        assert!(!policy.is_peer_allowed(&other_pk));
+    }
+
+    #[test]
+    fn test_verify_rejects_disallowed_scheme() {
+        let signed = SignedPublicKey {
+            key: PublicKey::default(),
+            timestamp: None,
+            scheme: SignatureScheme::Secp256k1,
+            signature: vec![],
+        };
+        assert!(signed
+            .verify(&[0u8; 33], &[SignatureScheme::P256])
+            .is_err());
+    }
+
+    #[test]
+    fn test_epoch_end_timestamp() {
+        // Epoch 0 ends one interval after genesis; epoch 41 ends 42
+        // intervals after genesis.
+        assert_eq!(epoch_end_timestamp(0, 1_000, 600), 1_600);
+        assert_eq!(epoch_end_timestamp(41, 1_000, 600), 1_000 + 42 * 600);
+    }
+
+    #[test]
+    fn test_is_epoch_key_available_within_grace_window() {
+        let key_epoch_end = 1_600;
+        let epoch_interval_secs = 600;
+        let grace_epochs = EPHEMERAL_KEY_GRACE_EPOCHS;
+
+        // Still current.
+        assert!(is_epoch_key_available(
+            key_epoch_end,
+            key_epoch_end,
+            epoch_interval_secs,
+            grace_epochs
+        ));
+        // Expired, but within the grace window.
+        assert!(is_epoch_key_available(
+            key_epoch_end,
+            key_epoch_end + epoch_interval_secs,
+            epoch_interval_secs,
+            grace_epochs
+        ));
+        // Past the grace window.
+        assert!(!is_epoch_key_available(
+            key_epoch_end,
+            key_epoch_end + 2 * epoch_interval_secs,
+            epoch_interval_secs,
+            grace_epochs
+        ));
+    }
 }